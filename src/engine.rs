@@ -0,0 +1,240 @@
+//! Glues a parsed `Ops` to the collection-specific `*_interact` function
+//! that actually runs it, and owns the `StateRef` shared by every
+//! connection. This is the dispatch layer `resp::ops::translate` hands off
+//! to once a command has been parsed off the wire.
+
+use crate::hashes::{self, HashOps};
+use crate::kv::{self, KvOps};
+use crate::lists::{self, ListOps};
+use crate::persistence;
+use crate::resp::ops::Ops;
+use crate::script::{self, RedisCaller};
+use crate::sets::{self, SetOps};
+use crate::transaction::ConnectionState;
+use crate::types::{Count, Key, ReturnValue, StateRef, Value};
+use std::future::Future;
+use std::pin::Pin;
+
+pub struct Engine {
+    pub state: StateRef,
+}
+
+impl Engine {
+    /// Builds an engine over `state`, loading `persistence::DEFAULT_DUMP_PATH`
+    /// into it first if a dump file is present. This is the server's boot
+    /// sequence: build the engine once here, before accepting connections.
+    pub fn new(state: StateRef) -> Self {
+        if let Err(e) = persistence::load_into(&state, persistence::DEFAULT_DUMP_PATH) {
+            eprintln!(
+                "warning: failed to load {}: {e}",
+                persistence::DEFAULT_DUMP_PATH
+            );
+        }
+        Engine { state }
+    }
+
+    /// Runs a single already-parsed command against the engine.
+    pub async fn dispatch(&self, op: Ops) -> ReturnValue {
+        let state = self.state.clone();
+        match op {
+            Ops::Pong => ReturnValue::StringRes(b"PONG".to_vec()),
+            Ops::Get(key) => kv::kv_interact(KvOps::Get(into_bytes(key)), state).await,
+            Ops::Set(key, val) => kv::kv_interact(KvOps::Set(into_bytes(key), into_bytes(val)), state).await,
+            Ops::Del(keys) => kv::kv_interact(KvOps::Del(many_bytes(keys)), state).await,
+            Ops::Rename(src, dest) => {
+                kv::kv_interact(KvOps::Rename(into_bytes(src), into_bytes(dest)), state).await
+            }
+            Ops::Exists(keys) => kv::kv_interact(KvOps::Exists(many_bytes(keys)), state).await,
+            Ops::Keys => kv::kv_interact(KvOps::Keys, state).await,
+            Ops::SAdd(key, members) => {
+                sets::set_interact(SetOps::SAdd(into_bytes(key), many_bytes(members)), state).await
+            }
+            Ops::SRem(key, members) => {
+                sets::set_interact(SetOps::SRem(into_bytes(key), many_bytes(members)), state).await
+            }
+            Ops::SMembers(key) => sets::set_interact(SetOps::SMembers(into_bytes(key)), state).await,
+            Ops::SIsMember(key, member) => {
+                sets::set_interact(SetOps::SIsMember(into_bytes(key), into_bytes(member)), state).await
+            }
+            Ops::SCard(key) => sets::set_interact(SetOps::SCard(into_bytes(key)), state).await,
+            Ops::SDiff(keys) => sets::set_interact(SetOps::SDiff(many_bytes(keys)), state).await,
+            Ops::SUnion(keys) => sets::set_interact(SetOps::SUnion(many_bytes(keys)), state).await,
+            Ops::SInter(keys) => sets::set_interact(SetOps::SInter(many_bytes(keys)), state).await,
+            Ops::SDiffStore(dest, keys) => {
+                sets::set_interact(SetOps::SDiffStore(into_bytes(dest), many_bytes(keys)), state).await
+            }
+            Ops::SUnionStore(dest, keys) => {
+                sets::set_interact(SetOps::SUnionStore(into_bytes(dest), many_bytes(keys)), state).await
+            }
+            Ops::SInterStore(dest, keys) => {
+                sets::set_interact(SetOps::SInterStore(into_bytes(dest), many_bytes(keys)), state).await
+            }
+            Ops::SPop(key, count) => {
+                sets::set_interact(SetOps::SPop(into_bytes(key), count.map(|c| c as i64)), state).await
+            }
+            Ops::SMove(src, dest, member) => {
+                sets::set_interact(
+                    SetOps::SMove(into_bytes(src), into_bytes(dest), into_bytes(member)),
+                    state,
+                )
+                .await
+            }
+            Ops::SRandMembers(key, count) => {
+                sets::set_interact(SetOps::SRandMembers(into_bytes(key), count), state).await
+            }
+            Ops::LPush(key, values) => {
+                lists::list_interact(ListOps::LPush(into_bytes(key), many_bytes(values)), state).await
+            }
+            Ops::LPushX(key, value) => {
+                lists::list_interact(ListOps::LPushX(into_bytes(key), into_bytes(value)), state).await
+            }
+            Ops::LLen(key) => lists::list_interact(ListOps::LLen(into_bytes(key)), state).await,
+            Ops::LPop(key) => lists::list_interact(ListOps::LPop(into_bytes(key)), state).await,
+            Ops::Save => match persistence::save(&state, persistence::DEFAULT_DUMP_PATH) {
+                Ok(()) => ReturnValue::Ok,
+                Err(_) => ReturnValue::Error(b"ERR SAVE failed"),
+            },
+            Ops::BgSave => {
+                persistence::bg_save(state, persistence::DEFAULT_DUMP_PATH);
+                ReturnValue::StringRes(b"Background saving started".to_vec())
+            }
+            Ops::Scan(cursor, pattern, count) => {
+                kv::kv_interact(
+                    KvOps::Scan(cursor, pattern.map(into_bytes), count.map(as_count)),
+                    state,
+                )
+                .await
+            }
+            Ops::SScan(key, cursor, pattern, count) => {
+                sets::set_interact(
+                    SetOps::SScan(into_bytes(key), cursor, pattern.map(into_bytes), count.map(as_count)),
+                    state,
+                )
+                .await
+            }
+            Ops::HScan(key, cursor, pattern, count) => {
+                hashes::hash_interact(
+                    HashOps::HScan(into_bytes(key), cursor, pattern.map(into_bytes), count.map(as_count)),
+                    state,
+                )
+                .await
+            }
+            Ops::Eval(body, keys, args) => {
+                let keys: Vec<Key> = keys.into_iter().map(String::into_bytes).collect();
+                let args: Vec<Value> = args.into_iter().map(String::into_bytes).collect();
+                let mut caller = EngineCaller { engine: self };
+                match script::run(body.as_bytes(), &keys, &args, &mut caller) {
+                    Ok(value) => value.into(),
+                    Err(e) => e.into(),
+                }
+            }
+            Ops::Multi | Ops::Exec | Ops::Discard => {
+                ReturnValue::Error(b"ERR MULTI/EXEC/DISCARD must go through handle_op")
+            }
+        }
+    }
+
+    /// Runs `op` for a connection in `conn`, honoring `MULTI`/`EXEC`/
+    /// `DISCARD` queuing instead of executing straight away.
+    ///
+    /// Sharding the keyspace removed the single lock `EXEC` used to hold for
+    /// the whole batch, and the collections' per-shard locks alone aren't
+    /// enough on a multi-threaded runtime: another connection's task can run
+    /// on a different OS thread at the exact moment between two of this
+    /// batch's commands, regardless of whether this task yields. So `EXEC`
+    /// also takes `state.exec_lock` as the sole writer for the batch's
+    /// duration (as do `EVAL`, for its whole script, and `SAVE`, for the
+    /// snapshot and the write to disk), while every other command takes it
+    /// as a reader first; since readers can run concurrently with each other
+    /// but never alongside the writer, no command from another connection
+    /// can interleave with a running transaction. Single commands outside a
+    /// transaction still run concurrently with each other against different
+    /// shards; while a writer holds the lock, no other command runs.
+    pub async fn handle_op(&self, conn: &mut ConnectionState, op: Ops) -> ReturnValue {
+        match op {
+            Ops::Multi => {
+                conn.begin_multi();
+                ReturnValue::Ok
+            }
+            Ops::Discard => {
+                conn.discard();
+                ReturnValue::Ok
+            }
+            Ops::Exec => {
+                let queued = conn.take_exec();
+                // `block_on_sync` rather than `.await`: holding a
+                // `parking_lot` guard across a real await point is exactly
+                // the kind of thing that can deadlock or starve other tasks,
+                // and clippy rightly flags it. It's fine here only because
+                // (as `EngineCaller` relies on below) `dispatch`'s future
+                // always resolves on its first poll.
+                let _guard = self.state.exec_lock.write();
+                let mut results = Vec::with_capacity(queued.len());
+                for queued_op in queued {
+                    results.push(block_on_sync(Box::pin(self.dispatch(queued_op))));
+                }
+                ReturnValue::Array(results)
+            }
+            op if conn.is_queuing() => {
+                conn.queue(op);
+                ReturnValue::StringRes(b"QUEUED".to_vec())
+            }
+            op @ (Ops::Eval(..) | Ops::Save) => {
+                let _guard = self.state.exec_lock.write();
+                block_on_sync(Box::pin(self.dispatch(op)))
+            }
+            op => {
+                let _guard = self.state.exec_lock.read();
+                block_on_sync(Box::pin(self.dispatch(op)))
+            }
+        }
+    }
+}
+
+fn into_bytes(s: String) -> Value {
+    s.into_bytes()
+}
+
+fn many_bytes(v: Vec<String>) -> Vec<Value> {
+    v.into_iter().map(String::into_bytes).collect()
+}
+
+fn as_count(c: usize) -> Count {
+    c as Count
+}
+
+/// Lets `EVAL` dispatch `redis.call` back through `Engine::dispatch`.
+///
+/// `dispatch` is `async fn`, but `RedisCaller::call` is synchronous (the
+/// evaluator is a plain tree-walker, not an async one). That's fine in
+/// practice: none of the `*_interact` functions an op can bottom out in
+/// ever actually await anything, so their futures always resolve on the first poll. `block_on_sync`
+/// drives exactly that one poll; if it ever sees `Pending` that's a sign
+/// something below it started doing real async work, which `redis.call`
+/// doesn't support.
+struct EngineCaller<'a> {
+    engine: &'a Engine,
+}
+
+impl RedisCaller for EngineCaller<'_> {
+    fn call(&mut self, op: Ops) -> ReturnValue {
+        block_on_sync(Box::pin(self.engine.dispatch(op)))
+    }
+}
+
+fn block_on_sync(mut fut: Pin<Box<dyn Future<Output = ReturnValue> + '_>>) -> ReturnValue {
+    use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+    fn noop(_: *const ()) {}
+    fn clone_noop(_: *const ()) -> RawWaker {
+        RawWaker::new(std::ptr::null(), &VTABLE)
+    }
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone_noop, noop, noop, noop);
+
+    let waker = unsafe { Waker::from_raw(RawWaker::new(std::ptr::null(), &VTABLE)) };
+    let mut cx = Context::from_waker(&waker);
+    match fut.as_mut().poll(&mut cx) {
+        Poll::Ready(v) => v,
+        Poll::Pending => ReturnValue::Error(b"redis.call blocked on a genuinely async operation"),
+    }
+}
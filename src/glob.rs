@@ -0,0 +1,216 @@
+//! Redis-style glob matching, used by the `SCAN` family's `MATCH` option.
+//!
+//! Supports `*` (any run, including empty), `?` (exactly one byte), `[...]`
+//! character classes with `[^...]` negation and `a-z` ranges, and `\` to
+//! escape the next metacharacter. Operates on raw bytes so binary-safe
+//! keys are matched correctly.
+
+/// A single piece of a compiled pattern: one pattern byte's worth of
+/// matching logic, with `[...]` classes pre-parsed so the matcher never has
+/// to reparse them while backtracking.
+enum Atom {
+    Literal(u8),
+    AnyByte,
+    Star,
+    Class { negate: bool, ranges: Vec<(u8, u8)> },
+}
+
+/// Returns true if `input` matches `pattern` under Redis glob semantics.
+pub fn glob_match(pattern: &[u8], input: &[u8]) -> bool {
+    do_match(&compile(pattern), input)
+}
+
+fn compile(pattern: &[u8]) -> Vec<Atom> {
+    let mut atoms = Vec::new();
+    let mut i = 0;
+    while i < pattern.len() {
+        match pattern[i] {
+            b'*' => {
+                atoms.push(Atom::Star);
+                i += 1;
+            }
+            b'?' => {
+                atoms.push(Atom::AnyByte);
+                i += 1;
+            }
+            b'\\' if i + 1 < pattern.len() => {
+                atoms.push(Atom::Literal(pattern[i + 1]));
+                i += 2;
+            }
+            b'[' => match parse_class(&pattern[i..]) {
+                Some((negate, ranges, consumed)) => {
+                    atoms.push(Atom::Class { negate, ranges });
+                    i += consumed;
+                }
+                // Unterminated class: treat '[' as a literal.
+                None => {
+                    atoms.push(Atom::Literal(b'['));
+                    i += 1;
+                }
+            },
+            c => {
+                atoms.push(Atom::Literal(c));
+                i += 1;
+            }
+        }
+    }
+    atoms
+}
+
+/// Inclusive byte ranges making up a `[...]` class (a bare char `c` is
+/// stored as `(c, c)`).
+type ClassRanges = Vec<(u8, u8)>;
+
+/// Parses a `[...]` class starting at `class[0]`. Returns `(negate, ranges,
+/// bytes_consumed)`, or `None` if the class is unterminated.
+fn parse_class(class: &[u8]) -> Option<(bool, ClassRanges, usize)> {
+    let mut i = 1; // skip the leading '['
+    let negate = class.get(i) == Some(&b'^');
+    if negate {
+        i += 1;
+    }
+    let mut ranges = Vec::new();
+    loop {
+        if i >= class.len() {
+            return None;
+        }
+        if class[i] == b']' {
+            i += 1;
+            break;
+        }
+        if class[i] == b'\\' && i + 1 < class.len() {
+            ranges.push((class[i + 1], class[i + 1]));
+            i += 2;
+        } else if class.get(i + 1) == Some(&b'-') && class.get(i + 2).is_some_and(|&e| e != b']') {
+            let (lo, hi) = (class[i], class[i + 2]);
+            ranges.push(if lo <= hi { (lo, hi) } else { (hi, lo) });
+            i += 3;
+        } else {
+            ranges.push((class[i], class[i]));
+            i += 1;
+        }
+    }
+    Some((negate, ranges, i))
+}
+
+fn class_matches(ranges: &[(u8, u8)], negate: bool, c: u8) -> bool {
+    let hit = ranges.iter().any(|&(lo, hi)| lo <= c && c <= hi);
+    hit != negate
+}
+
+/// Iterative matcher: a pattern with several `*`s used to recurse into every
+/// suffix of `input` at each one, which is exponential in the number of
+/// `*`s (e.g. `a*a*a*...*b` against a long run of `a`s). Instead this keeps
+/// only the *most recent* `*`'s position and retries by having it eat one
+/// more byte on mismatch, same as the classic linear-ish wildcard-matching
+/// algorithm (and real Redis's `stringmatchlen`) -- no recursion, and no
+/// blowup from multiple `*`s.
+fn do_match(atoms: &[Atom], input: &[u8]) -> bool {
+    let mut pi = 0;
+    let mut si = 0;
+    let mut star_pi: Option<usize> = None;
+    let mut star_si = 0;
+
+    loop {
+        if pi < atoms.len() {
+            if let Atom::Star = atoms[pi] {
+                star_pi = Some(pi + 1);
+                star_si = si;
+                pi += 1;
+                continue;
+            }
+            let byte_matches = si < input.len()
+                && match &atoms[pi] {
+                    Atom::Literal(c) => input[si] == *c,
+                    Atom::AnyByte => true,
+                    Atom::Class { negate, ranges } => class_matches(ranges, *negate, input[si]),
+                    Atom::Star => unreachable!("handled above"),
+                };
+            if byte_matches {
+                pi += 1;
+                si += 1;
+                continue;
+            }
+        } else if si == input.len() {
+            return true;
+        }
+
+        // Mismatch, or pattern exhausted with input left over: back up to
+        // the most recent '*' and have it consume one more byte, if any are
+        // left for it to consume.
+        match star_pi {
+            Some(retry_pi) if star_si < input.len() => {
+                star_si += 1;
+                si = star_si;
+                pi = retry_pi;
+            }
+            _ => return false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::glob_match;
+
+    fn m(pattern: &str, input: &str) -> bool {
+        glob_match(pattern.as_bytes(), input.as_bytes())
+    }
+
+    #[test]
+    fn literal() {
+        assert!(m("foo", "foo"));
+        assert!(!m("foo", "foobar"));
+        assert!(!m("foo", "fo"));
+    }
+
+    #[test]
+    fn star() {
+        assert!(m("*", ""));
+        assert!(m("*", "anything"));
+        assert!(m("foo*", "foobar"));
+        assert!(m("*bar", "foobar"));
+        assert!(m("f*r", "foobar"));
+        assert!(!m("f*r", "foobaz"));
+    }
+
+    #[test]
+    fn question_mark() {
+        assert!(m("f?o", "foo"));
+        assert!(!m("f?o", "fo"));
+        assert!(!m("f?o", "fooo"));
+    }
+
+    #[test]
+    fn class() {
+        assert!(m("[abc]", "a"));
+        assert!(!m("[abc]", "d"));
+        assert!(m("[a-c]", "b"));
+        assert!(!m("[a-c]", "d"));
+        assert!(m("[^abc]", "d"));
+        assert!(!m("[^abc]", "a"));
+    }
+
+    #[test]
+    fn escape() {
+        assert!(m(r"\*", "*"));
+        assert!(!m(r"\*", "a"));
+    }
+
+    #[test]
+    fn binary_safe() {
+        assert!(glob_match(b"*", b"\xff\x00\xfe"));
+        assert!(glob_match(b"\xff*", b"\xff\x00\xfe"));
+    }
+
+    #[test]
+    fn many_stars_does_not_blow_up() {
+        // Regression test: this pattern's recursive backtracking used to
+        // double in cost per extra "a*", making this call take over a
+        // second; the iterative matcher handles it instantly.
+        let pattern = "a*".repeat(30) + "b";
+        let input = "a".repeat(40);
+        assert!(!m(&pattern, &input));
+        assert!(m(&("a*".repeat(30) + "b"), &(("a".repeat(40)) + "b")));
+    }
+}
@@ -1,8 +1,12 @@
+use crate::glob::glob_match;
 use crate::op_variants;
 use crate::types::{Count, Key, ReturnValue, StateRef, Value};
-use crate::{make_reader, make_writer};
 use std::collections::hash_map::Entry;
 
+/// Default number of elements returned by a single HSCAN call when no
+/// COUNT is given, matching real Redis.
+const DEFAULT_SCAN_COUNT: Count = 10;
+
 op_variants! {
     HashOps,
     HGet(Key, Key),
@@ -17,7 +21,8 @@ op_variants! {
     HDel(Key, Vec<Key>),
     HVals(Key),
     HStrLen(Key, Key),
-    HSetNX(Key, Key, Value)
+    HSetNX(Key, Key, Value),
+    HScan(Key, Count, Option<Key>, Option<Count>)
 }
 
 macro_rules! ops_error {
@@ -26,62 +31,69 @@ macro_rules! ops_error {
     };
 }
 
-make_reader!(hashes, read_hashes);
-make_writer!(hashes, write_hashes);
 pub async fn hash_interact(hash_op: HashOps, state: StateRef) -> ReturnValue {
     match hash_op {
-        HashOps::HGet(key, field) => read_hashes!(state)
+        // Single-key ops only ever need their own key's shard, so they
+        // lock through `state.hashes.{read,write}_shard(&key)` directly
+        // rather than the collection-wide lock the old single-`RwLock`
+        // `hashes` map used.
+        HashOps::HGet(key, field) => state
+            .hashes
+            .read_shard(&key)
             .get(&key)
             .and_then(|hashes| hashes.get(&field))
             .map_or(ReturnValue::Nil, |v| ReturnValue::StringRes(v.clone())),
         HashOps::HSet(key, field, value) => {
-            let mut hash_lock = state.hashes.write();
+            let mut hash_lock = state.hashes.write_shard(&key);
             hash_lock.entry(key).or_default().insert(field, value);
             ReturnValue::Ok
         }
-        HashOps::HExists(key, field) => read_hashes!(state)
+        HashOps::HExists(key, field) => state
+            .hashes
+            .read_shard(&key)
             .get(&key)
             .map(|hashes| hashes.contains_key(&field))
             .map_or(ReturnValue::IntRes(0), |v: bool| {
                 ReturnValue::IntRes(if v { 1 } else { 0 })
             }),
-        HashOps::HGetAll(key) => {
-            read_hashes!(state, &key, hash);
-            if hash.is_none() {
-                return ReturnValue::MultiStringRes(vec![]);
-            }
-            let mut ret = Vec::new();
-            for (key, val) in hash.unwrap().iter() {
-                ret.push(key.clone());
-                ret.push(val.clone());
+        HashOps::HGetAll(key) => match state.hashes.read_shard(&key).get(&key) {
+            None => ReturnValue::MultiStringRes(vec![]),
+            Some(hash) => {
+                let mut ret = Vec::new();
+                for (key, val) in hash.iter() {
+                    ret.push(key.clone());
+                    ret.push(val.clone());
+                }
+                ReturnValue::MultiStringRes(ret)
             }
-            ReturnValue::MultiStringRes(ret)
+        },
+        HashOps::HMGet(key, fields) => {
+            ReturnValue::Array(match state.hashes.read_shard(&key).get(&key) {
+                None => std::iter::repeat_with(|| ReturnValue::Nil)
+                    .take(fields.len())
+                    .collect(),
+                Some(hash) => fields
+                    .iter()
+                    .map(|field| {
+                        hash.get(field)
+                            .map_or(ReturnValue::Nil, |v| ReturnValue::StringRes(v.clone()))
+                    })
+                    .collect(),
+            })
         }
-        HashOps::HMGet(key, fields) => ReturnValue::Array(match read_hashes!(state, &key) {
-            None => std::iter::repeat_with(|| ReturnValue::Nil)
-                .take(fields.len())
-                .collect(),
-            Some(hash) => fields
-                .iter()
-                .map(|field| {
-                    hash.get(field)
-                        .map_or(ReturnValue::Nil, |v| ReturnValue::StringRes(v.clone()))
-                })
-                .collect(),
-        }),
-        HashOps::HKeys(key) => match read_hashes!(state, &key) {
+        HashOps::HKeys(key) => match state.hashes.read_shard(&key).get(&key) {
             Some(hash) => {
                 ReturnValue::Array(hash.keys().cloned().map(ReturnValue::StringRes).collect())
             }
             None => ReturnValue::Array(vec![]),
         },
         HashOps::HMSet(key, key_values) => {
-            let mut hash_lock = state.hashes.write();
+            let mut hash_lock = state.hashes.write_shard(&key);
             hash_lock.entry(key).or_default().extend(key_values);
             ReturnValue::Ok
         }
         HashOps::HIncrBy(key, field, count) => {
-            let mut hash_lock = state.hashes.write();
+            let mut hash_lock = state.hashes.write_shard(&key);
             let hash = hash_lock.entry(key).or_default();
             let mut curr_value = match hash.get(&field) {
                 Some(value) => {
@@ -100,39 +112,35 @@ pub async fn hash_interact(hash_op: HashOps, state: StateRef) -> ReturnValue {
             hash.insert(field, new_value);
             ReturnValue::Ok
         }
-        HashOps::HLen(key) => read_hashes!(state, &key)
+        HashOps::HLen(key) => state
+            .hashes
+            .read_shard(&key)
+            .get(&key)
             .map_or(0, |hash| hash.len() as Count)
             .into(),
-
-        // HashOps::HLen(key) => read_hashes!(state, &key)
-        //     .map(|hash| hash.len() as Count)
-        //     .unwrap_or(0)
-        //     .into(),
-        // HashOps::HLen(key) => match read_hashes!(state, &key) {
-        //     Some(hash) => ReturnValue::IntRes(hash.len() as Count),
-        //     None => ReturnValue::IntRes(0),
-        // },
-        HashOps::HDel(key, fields) => match write_hashes!(state, &key) {
+        HashOps::HDel(key, fields) => match state.hashes.write_shard(&key).get_mut(&key) {
             Some(hash) => {
                 let res = fields.iter().filter_map(|field| hash.remove(field)).count();
                 ReturnValue::IntRes(res as Count)
             }
             None => ReturnValue::IntRes(0),
         },
-        HashOps::HVals(key) => match read_hashes!(state, &key) {
+        HashOps::HVals(key) => match state.hashes.read_shard(&key).get(&key) {
             Some(hash) => {
                 ReturnValue::Array(hash.values().cloned().map(ReturnValue::StringRes).collect())
             }
             None => ReturnValue::Array(vec![]),
         },
-        HashOps::HStrLen(key, field) => read_hashes!(state)
+        HashOps::HStrLen(key, field) => state
+            .hashes
+            .read_shard(&key)
             .get(&key)
             .and_then(|hashes| hashes.get(&field))
             .map_or(ReturnValue::IntRes(0), |v| {
                 ReturnValue::IntRes(v.len() as Count)
             }),
         HashOps::HSetNX(key, field, value) => {
-            let mut hash_lock = state.hashes.write();
+            let mut hash_lock = state.hashes.write_shard(&key);
             if let Entry::Vacant(ent) = hash_lock.entry(key).or_default().entry(field) {
                 ent.insert(value);
                 ReturnValue::IntRes(1)
@@ -140,5 +148,39 @@ pub async fn hash_interact(hash_op: HashOps, state: StateRef) -> ReturnValue {
                 ReturnValue::IntRes(0)
             }
         }
+        HashOps::HScan(key, cursor, pattern, count) => {
+            let count = count.unwrap_or(DEFAULT_SCAN_COUNT).max(1);
+            let hashes = state.hashes.read_shard(&key);
+            let hash = match hashes.get(&key) {
+                Some(hash) => hash,
+                None => return ReturnValue::Array(vec![ReturnValue::StringRes(b"0".to_vec()), ReturnValue::Array(vec![])]),
+            };
+            // HashMap iteration order is unstable, so the cursor is an
+            // offset into a lexicographically sorted snapshot of the
+            // hash's fields taken at the start of this call.
+            let mut fields: Vec<&Key> = hash.keys().collect();
+            fields.sort();
+            let start = cursor.max(0) as usize;
+            let mut elements = Vec::new();
+            let mut scanned = start;
+            for field in fields.iter().skip(start) {
+                scanned += 1;
+                if let Some(pattern) = &pattern {
+                    if !glob_match(pattern, field) {
+                        continue;
+                    }
+                }
+                elements.push(ReturnValue::StringRes((*field).clone()));
+                elements.push(ReturnValue::StringRes(hash.get(*field).unwrap().clone()));
+                if elements.len() / 2 >= count as usize {
+                    break;
+                }
+            }
+            let new_cursor = if scanned >= fields.len() { 0 } else { scanned };
+            ReturnValue::Array(vec![
+                ReturnValue::StringRes(new_cursor.to_string().into_bytes()),
+                ReturnValue::Array(elements),
+            ])
+        }
     }
 }
@@ -0,0 +1,123 @@
+//! Plain key/value operations against `state.kv`, sharded like every other
+//! collection. `DEL`, `RENAME` and `EXISTS` can touch more than one key at
+//! once, so they lock every shard involved up front with `write_many`/
+//! `read_many` instead of locking one key at a time.
+
+use crate::op_variants;
+use crate::types::{Count, Key, ReturnValue, StateRef, Value};
+
+op_variants! {
+    KvOps,
+    Get(Key),
+    Set(Key, Value),
+    Del(Vec<Key>),
+    Rename(Key, Key),
+    Exists(Vec<Key>),
+    Keys,
+    Scan(Count, Option<Key>, Option<Count>)
+}
+
+const DEFAULT_SCAN_COUNT: Count = 10;
+
+pub async fn kv_interact(op: KvOps, state: StateRef) -> ReturnValue {
+    match op {
+        KvOps::Get(key) => state
+            .kv
+            .read_shard(&key)
+            .get(&key)
+            .map_or(ReturnValue::Nil, |v| ReturnValue::StringRes(v.clone())),
+        KvOps::Set(key, value) => {
+            state.kv.write_shard(&key).insert(key, value);
+            ReturnValue::Ok
+        }
+        KvOps::Del(keys) => {
+            let key_refs: Vec<&Key> = keys.iter().collect();
+            let mut shards = state.kv.write_many(&key_refs);
+            let removed = keys
+                .iter()
+                .filter(|key| {
+                    let idx = state.kv.shard_of(key);
+                    shards
+                        .iter_mut()
+                        .find(|(i, _)| *i == idx)
+                        .is_some_and(|(_, shard)| shard.remove(*key).is_some())
+                })
+                .count();
+            ReturnValue::IntRes(removed as Count)
+        }
+        KvOps::Rename(src, dest) => {
+            let key_refs = [&src, &dest];
+            let mut shards = state.kv.write_many(&key_refs);
+            let src_idx = state.kv.shard_of(&src);
+            let dest_idx = state.kv.shard_of(&dest);
+            let value = shards
+                .iter_mut()
+                .find(|(i, _)| *i == src_idx)
+                .and_then(|(_, shard)| shard.remove(&src));
+            match value {
+                Some(value) => {
+                    if let Some((_, shard)) = shards.iter_mut().find(|(i, _)| *i == dest_idx) {
+                        shard.insert(dest, value);
+                    }
+                    ReturnValue::Ok
+                }
+                None => ReturnValue::Error(b"ERR no such key"),
+            }
+        }
+        KvOps::Exists(keys) => {
+            // Locked as one snapshot via `read_many`, not key-at-a-time, so a
+            // concurrent write between two of these keys can't make the
+            // count reflect a mix of before- and after- states.
+            let key_refs: Vec<&Key> = keys.iter().collect();
+            let shards = state.kv.read_many(&key_refs);
+            let count = keys
+                .iter()
+                .filter(|key| {
+                    let idx = state.kv.shard_of(key);
+                    shards
+                        .iter()
+                        .find(|(i, _)| *i == idx)
+                        .is_some_and(|(_, shard)| shard.contains_key(*key))
+                })
+                .count();
+            ReturnValue::IntRes(count as Count)
+        }
+        KvOps::Keys => {
+            let mut keys = Vec::new();
+            for shard in state.kv.read_all() {
+                keys.extend(shard.keys().cloned().map(ReturnValue::StringRes));
+            }
+            ReturnValue::Array(keys)
+        }
+        KvOps::Scan(cursor, pattern, count) => {
+            let count = count.unwrap_or(DEFAULT_SCAN_COUNT).max(1);
+            // Same cursor model as HSCAN: an offset into a lexicographically
+            // sorted snapshot of the keyspace taken at the start of the call.
+            let mut keys = Vec::new();
+            for shard in state.kv.read_all() {
+                keys.extend(shard.keys().cloned());
+            }
+            keys.sort();
+            let start = cursor.max(0) as usize;
+            let mut elements = Vec::new();
+            let mut scanned = start;
+            for key in keys.iter().skip(start) {
+                scanned += 1;
+                if let Some(pattern) = &pattern {
+                    if !crate::glob::glob_match(pattern, key) {
+                        continue;
+                    }
+                }
+                elements.push(ReturnValue::StringRes(key.clone()));
+                if elements.len() as Count >= count {
+                    break;
+                }
+            }
+            let new_cursor = if scanned >= keys.len() { 0 } else { scanned };
+            ReturnValue::Array(vec![
+                ReturnValue::StringRes(new_cursor.to_string().into_bytes()),
+                ReturnValue::Array(elements),
+            ])
+        }
+    }
+}
@@ -0,0 +1,44 @@
+//! List operations against `state.lists`, sharded like every other
+//! collection. Every list command here only ever touches one key, so each
+//! just locks that key's shard.
+
+use crate::op_variants;
+use crate::types::{Count, Key, ReturnValue, StateRef, Value};
+
+op_variants! {
+    ListOps,
+    LPush(Key, Vec<Value>),
+    LPushX(Key, Value),
+    LLen(Key),
+    LPop(Key)
+}
+
+pub async fn list_interact(op: ListOps, state: StateRef) -> ReturnValue {
+    match op {
+        ListOps::LPush(key, values) => {
+            let mut shard = state.lists.write_shard(&key);
+            let list = shard.entry(key).or_default();
+            for value in values {
+                list.insert(0, value);
+            }
+            ReturnValue::IntRes(list.len() as Count)
+        }
+        ListOps::LPushX(key, value) => match state.lists.write_shard(&key).get_mut(&key) {
+            Some(list) => {
+                list.insert(0, value);
+                ReturnValue::IntRes(list.len() as Count)
+            }
+            None => ReturnValue::IntRes(0),
+        },
+        ListOps::LLen(key) => state
+            .lists
+            .read_shard(&key)
+            .get(&key)
+            .map_or(0, |list| list.len() as Count)
+            .into(),
+        ListOps::LPop(key) => match state.lists.write_shard(&key).get_mut(&key) {
+            Some(list) if !list.is_empty() => ReturnValue::StringRes(list.remove(0)),
+            _ => ReturnValue::Nil,
+        },
+    }
+}
@@ -0,0 +1,148 @@
+//! CBOR snapshots of the keyspace, for `SAVE`/`BGSAVE` and load-on-startup.
+
+use crate::types::{Key, StateRef, Value};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::path::Path;
+
+/// Default dump file name, written to the working directory.
+pub const DEFAULT_DUMP_PATH: &str = "dump.rdb";
+
+/// An owned, point-in-time copy of every collection in `StateRef`, in the
+/// shape that gets written to and read back from the dump file.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct StateSnapshot {
+    pub kv: HashMap<Key, Value>,
+    pub hashes: HashMap<Key, HashMap<Key, Value>>,
+    pub sets: HashMap<Key, HashSet<Value>>,
+    pub lists: HashMap<Key, Vec<Value>>,
+}
+
+impl StateSnapshot {
+    /// Clones every shard of every collection out of `state` in turn. Used
+    /// for both `SAVE` and `BGSAVE`; `BGSAVE` just does the expensive
+    /// encode-and-write part after releasing the locks.
+    fn capture(state: &StateRef) -> Self {
+        StateSnapshot {
+            kv: state.kv.snapshot(),
+            hashes: state.hashes.snapshot(),
+            sets: state.sets.snapshot(),
+            lists: state.lists.snapshot(),
+        }
+    }
+
+    /// Overwrites every collection in `state` with this snapshot's contents,
+    /// redistributing each entry back into its owning shard.
+    fn restore_into(self, state: &StateRef) {
+        state.kv.replace_all(self.kv);
+        state.hashes.replace_all(self.hashes);
+        state.sets.replace_all(self.sets);
+        state.lists.replace_all(self.lists);
+    }
+
+    fn write_to(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let file = std::fs::File::create(path)?;
+        serde_cbor::to_writer(file, self).map_err(io::Error::other)
+    }
+
+    fn read_from(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = std::fs::File::open(path)?;
+        serde_cbor::from_reader(file).map_err(io::Error::other)
+    }
+}
+
+/// `SAVE`: synchronously snapshot and flush to disk. `Engine::handle_op`
+/// runs it as the sole holder of `exec_lock`, blocking every other command
+/// for the duration (matching real Redis's semantics).
+pub fn save(state: &StateRef, path: impl AsRef<Path>) -> io::Result<()> {
+    StateSnapshot::capture(state).write_to(path)
+}
+
+/// `BGSAVE`: take the snapshot synchronously (cheap: just clones under each
+/// collection's lock in turn), then hand the slow encode-and-write off to a
+/// background task and return immediately, so the caller isn't blocked on
+/// disk I/O. A write failure is logged rather than reported back, since
+/// there's no caller left to report it to by the time it happens.
+pub fn bg_save(state: StateRef, path: impl AsRef<Path> + Send + 'static) {
+    let snapshot = StateSnapshot::capture(&state);
+    tokio::task::spawn_blocking(move || {
+        if let Err(e) = snapshot.write_to(path) {
+            eprintln!("BGSAVE failed: {e}");
+        }
+    });
+}
+
+/// Loads `path` into `state` if it exists, leaving `state` untouched
+/// otherwise. Call this before accepting connections at startup.
+pub fn load_into(state: &StateRef, path: impl AsRef<Path>) -> io::Result<()> {
+    if !path.as_ref().exists() {
+        return Ok(());
+    }
+    StateSnapshot::read_from(path)?.restore_into(state);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::State;
+    use std::sync::Arc;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("redis-oxide-test-{}-{name}.rdb", std::process::id()))
+    }
+
+    #[test]
+    fn write_to_read_from_round_trip() {
+        let path = temp_path("write-read");
+        let mut snapshot = StateSnapshot::default();
+        snapshot.kv.insert(b"key".to_vec(), b"value".to_vec());
+        snapshot
+            .sets
+            .insert(b"set".to_vec(), [b"a".to_vec(), b"b".to_vec()].into_iter().collect());
+
+        snapshot.write_to(&path).unwrap();
+        let read_back = StateSnapshot::read_from(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(read_back.kv, snapshot.kv);
+        assert_eq!(read_back.sets, snapshot.sets);
+    }
+
+    #[test]
+    fn save_and_load_into_round_trip_through_state() {
+        let path = temp_path("save-load");
+        let state: StateRef = Arc::new(State::new());
+        state.kv.write_shard(&b"k".to_vec()).insert(b"k".to_vec(), b"v".to_vec());
+        state
+            .hashes
+            .write_shard(&b"h".to_vec())
+            .entry(b"h".to_vec())
+            .or_default()
+            .insert(b"field".to_vec(), b"value".to_vec());
+
+        save(&state, &path).unwrap();
+
+        let loaded: StateRef = Arc::new(State::new());
+        load_into(&loaded, &path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(loaded.kv.read_shard(&b"k".to_vec()).get(b"k".as_slice()), Some(&b"v".to_vec()));
+        assert_eq!(
+            loaded.hashes.read_shard(&b"h".to_vec()).get(b"h".as_slice()).and_then(|m| m.get(b"field".as_slice())),
+            Some(&b"value".to_vec())
+        );
+    }
+
+    #[test]
+    fn load_into_is_a_no_op_when_the_file_is_missing() {
+        let path = temp_path("missing");
+        let state: StateRef = Arc::new(State::new());
+        state.kv.write_shard(&b"k".to_vec()).insert(b"k".to_vec(), b"v".to_vec());
+
+        load_into(&state, &path).unwrap();
+
+        assert_eq!(state.kv.read_shard(&b"k".to_vec()).get(b"k".as_slice()), Some(&b"v".to_vec()));
+    }
+}
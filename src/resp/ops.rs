@@ -36,9 +36,22 @@ pub enum Ops {
     Keys, // TODO: Add optional glob
     Exists(Vec<Key>),
     Pong,
+    // Scanning
+    Scan(ICount, Option<String>, Option<Count>),
+    SScan(Key, ICount, Option<String>, Option<Count>),
+    HScan(Key, ICount, Option<String>, Option<Count>),
+    // Persistence
+    Save,
+    BgSave,
+    // Scripting
+    Eval(String, Vec<Key>, Vec<String>),
+    // Transactions
+    Multi,
+    Exec,
+    Discard,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum OpsError {
     InvalidStart,
     Noop,
@@ -103,6 +116,11 @@ fn translate_string(start: &str) -> Result<Ops, OpsError> {
     match start.to_lowercase().as_ref() {
         "ping" => Ok(Ops::Pong),
         "keys" => Ok(Ops::Keys),
+        "save" => Ok(Ops::Save),
+        "bgsave" => Ok(Ops::BgSave),
+        "multi" => Ok(Ops::Multi),
+        "exec" => Ok(Ops::Exec),
+        "discard" => Ok(Ops::Discard),
         _ => Err(OpsError::UnknownOp),
     }
 }
@@ -156,6 +174,25 @@ fn get_key_and_tail(array: &[RedisValue]) -> Result<(Key, Vec<String>), OpsError
     Ok((set_key, vals))
 }
 
+/// Parses the trailing `[MATCH pattern] [COUNT n]` options shared by the
+/// `SCAN`-family commands, in either order.
+fn parse_scan_options(tail: &[&RedisValue]) -> Result<(Option<String>, Option<Count>), OpsError> {
+    let mut pattern = None;
+    let mut count = None;
+    let mut i = 0;
+    while i < tail.len() {
+        let opt = String::try_from(tail[i])?.to_lowercase();
+        let val = tail.get(i + 1).ok_or(OpsError::SyntaxError)?;
+        match opt.as_ref() {
+            "match" => pattern = Some(String::try_from(*val)?),
+            "count" => count = Some(Count::try_from(*val)?),
+            _ => return Err(OpsError::SyntaxError),
+        }
+        i += 2;
+    }
+    Ok((pattern, count))
+}
+
 fn translate_array(array: &[RedisValue]) -> Result<Ops, OpsError> {
     if array.is_empty() {
         return Err(OpsError::Noop);
@@ -292,6 +329,37 @@ fn translate_array(array: &[RedisValue]) -> Result<Ops, OpsError> {
             let key = String::try_from(tail[0])?;
             Ok(Ops::LPop(key))
         }
+        "scan" => {
+            verify_size_lower(&tail, 1)?;
+            let cursor = ICount::try_from(tail[0])?;
+            let (pattern, count) = parse_scan_options(&tail[1..])?;
+            Ok(Ops::Scan(cursor, pattern, count))
+        }
+        "sscan" => {
+            verify_size_lower(&tail, 2)?;
+            let key = String::try_from(tail[0])?;
+            let cursor = ICount::try_from(tail[1])?;
+            let (pattern, count) = parse_scan_options(&tail[2..])?;
+            Ok(Ops::SScan(key, cursor, pattern, count))
+        }
+        "hscan" => {
+            verify_size_lower(&tail, 2)?;
+            let key = String::try_from(tail[0])?;
+            let cursor = ICount::try_from(tail[1])?;
+            let (pattern, count) = parse_scan_options(&tail[2..])?;
+            Ok(Ops::HScan(key, cursor, pattern, count))
+        }
+        "eval" => {
+            verify_size_lower(&tail, 2)?;
+            let script = String::try_from(tail[0])?;
+            let num_keys = Count::try_from(tail[1])?;
+            if tail.len() < 2 + num_keys {
+                return Err(OpsError::NotEnoughArgs(2 + num_keys));
+            }
+            let keys = tails_as_strings(&tail[2..2 + num_keys])?;
+            let args = tails_as_strings(&tail[2 + num_keys..])?;
+            Ok(Ops::Eval(script, keys, args))
+        }
         _ => Err(OpsError::UnknownOp),
     }
 }
@@ -0,0 +1,169 @@
+//! Evaluates a parsed script body against `KEYS`/`ARGV` and a `redis.call`
+//! callback, producing a `ScriptValue`.
+
+use super::expr::{BinOp, Expr};
+use super::value::ScriptValue;
+use super::ScriptError;
+use crate::resp::ops::{translate, Ops};
+use crate::resp::resp::RedisValue;
+use crate::types::{Key, ReturnValue, Value};
+use std::collections::HashMap;
+
+/// Runs `redis.call` by constructing an `Ops` through the same
+/// `translate`/dispatch path a real client request would go through, and
+/// running it against the engine. Implementations get to decide how (and
+/// under what lock) that happens; the evaluator just needs the result back.
+pub trait RedisCaller {
+    fn call(&mut self, op: Ops) -> ReturnValue;
+}
+
+struct Env<'a, C: RedisCaller> {
+    keys: &'a [Key],
+    args: &'a [Value],
+    vars: HashMap<String, ScriptValue>,
+    caller: &'a mut C,
+}
+
+pub fn eval_block<C: RedisCaller>(
+    block: &[Expr],
+    keys: &[Key],
+    args: &[Value],
+    caller: &mut C,
+) -> Result<ScriptValue, ScriptError> {
+    let mut env = Env {
+        keys,
+        args,
+        vars: HashMap::new(),
+        caller,
+    };
+    let mut last = ScriptValue::Nil;
+    for expr in block {
+        last = eval_expr(expr, &mut env)?;
+    }
+    Ok(last)
+}
+
+fn eval_expr<C: RedisCaller>(expr: &Expr, env: &mut Env<'_, C>) -> Result<ScriptValue, ScriptError> {
+    match expr {
+        Expr::Int(n) => Ok(ScriptValue::Int(*n)),
+        Expr::Bytes(b) => Ok(ScriptValue::Bytes(b.clone())),
+        Expr::Var(name) => env
+            .vars
+            .get(name)
+            .cloned()
+            .ok_or_else(|| ScriptError::UnknownVariable(name.clone())),
+        Expr::KeysIndex(idx) => {
+            let i = as_index(eval_expr(idx, env)?)?;
+            env.keys
+                .get(i)
+                .map(|k| ScriptValue::Bytes(k.clone()))
+                .ok_or(ScriptError::IndexOutOfBounds)
+        }
+        Expr::ArgvIndex(idx) => {
+            let i = as_index(eval_expr(idx, env)?)?;
+            env.args
+                .get(i)
+                .map(|v| ScriptValue::Bytes(v.clone()))
+                .ok_or(ScriptError::IndexOutOfBounds)
+        }
+        Expr::Let(name, value) => {
+            let v = eval_expr(value, env)?;
+            env.vars.insert(name.clone(), v.clone());
+            Ok(v)
+        }
+        Expr::If(cond, then_block, else_block) => {
+            let branch = if eval_expr(cond, env)?.is_truthy() {
+                then_block
+            } else {
+                else_block
+            };
+            let mut last = ScriptValue::Nil;
+            for expr in branch {
+                last = eval_expr(expr, env)?;
+            }
+            Ok(last)
+        }
+        Expr::BinOp(op, lhs, rhs) => {
+            let lhs = eval_expr(lhs, env)?;
+            let rhs = eval_expr(rhs, env)?;
+            eval_binop(*op, lhs, rhs)
+        }
+        Expr::RedisCall(arg_exprs) => {
+            if arg_exprs.is_empty() {
+                return Err(ScriptError::InvalidCallArgs);
+            }
+            let mut values = Vec::with_capacity(arg_exprs.len());
+            for e in arg_exprs {
+                values.push(eval_expr(e, env)?);
+            }
+            let parts = values
+                .iter()
+                .map(|v| script_value_to_string(v).map(RedisValue::BulkString))
+                .collect::<Result<Vec<_>, _>>()?;
+            let op = translate(&RedisValue::Array(parts)).map_err(ScriptError::CallTranslate)?;
+            Ok(return_value_to_script_value(env.caller.call(op)))
+        }
+    }
+}
+
+fn as_index(v: ScriptValue) -> Result<usize, ScriptError> {
+    match v {
+        ScriptValue::Int(n) if n >= 0 => Ok(n as usize),
+        _ => Err(ScriptError::InvalidIndex),
+    }
+}
+
+// `redis.call` goes through `resp::ops::translate`, which (like the rest of
+// that module) works in `String` rather than raw bytes, so a call argument
+// has to round-trip through UTF-8.
+fn script_value_to_string(v: &ScriptValue) -> Result<String, ScriptError> {
+    match v {
+        ScriptValue::Int(n) => Ok(n.to_string()),
+        ScriptValue::Bytes(b) => String::from_utf8(b.clone()).map_err(|_| ScriptError::InvalidCallArgs),
+        ScriptValue::Nil | ScriptValue::Array(_) => Err(ScriptError::InvalidCallArgs),
+    }
+}
+
+fn eval_binop(op: BinOp, lhs: ScriptValue, rhs: ScriptValue) -> Result<ScriptValue, ScriptError> {
+    use BinOp::*;
+    if matches!(op, Eq | Neq) {
+        let equal = lhs == rhs;
+        return Ok(ScriptValue::Int(if (op == Eq) == equal { 1 } else { 0 }));
+    }
+    let (l, r) = match (lhs, rhs) {
+        (ScriptValue::Int(l), ScriptValue::Int(r)) => (l, r),
+        _ => return Err(ScriptError::TypeError),
+    };
+    Ok(match op {
+        Add => ScriptValue::Int(l.checked_add(r).ok_or(ScriptError::IntegerOverflow)?),
+        Sub => ScriptValue::Int(l.checked_sub(r).ok_or(ScriptError::IntegerOverflow)?),
+        Mul => ScriptValue::Int(l.checked_mul(r).ok_or(ScriptError::IntegerOverflow)?),
+        Div => {
+            if r == 0 {
+                return Err(ScriptError::DivideByZero);
+            }
+            ScriptValue::Int(l / r)
+        }
+        Lt => ScriptValue::Int((l < r) as i64),
+        Gt => ScriptValue::Int((l > r) as i64),
+        Le => ScriptValue::Int((l <= r) as i64),
+        Ge => ScriptValue::Int((l >= r) as i64),
+        Eq | Neq => unreachable!("handled above"),
+    })
+}
+
+fn return_value_to_script_value(rv: ReturnValue) -> ScriptValue {
+    match rv {
+        ReturnValue::Ok => ScriptValue::Bytes(b"OK".to_vec()),
+        ReturnValue::Nil => ScriptValue::Nil,
+        ReturnValue::StringRes(v) => ScriptValue::Bytes(v),
+        ReturnValue::IntRes(n) => ScriptValue::Int(n),
+        ReturnValue::MultiStringRes(vs) => {
+            ScriptValue::Array(vs.into_iter().map(ScriptValue::Bytes).collect())
+        }
+        ReturnValue::Array(vs) => {
+            ScriptValue::Array(vs.into_iter().map(return_value_to_script_value).collect())
+        }
+        ReturnValue::Error(_) => ScriptValue::Nil,
+    }
+}
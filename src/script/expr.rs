@@ -0,0 +1,39 @@
+//! The AST that `EVAL` scripts parse down to. Kept deliberately small:
+//! literals, `KEYS`/`ARGV` indexing, `let` bindings, `if`, arithmetic and
+//! comparison, and the `redis.call` builtin.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Eq,
+    Neq,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Int(i64),
+    Bytes(Vec<u8>),
+    /// A reference to a `let`-bound name.
+    Var(String),
+    /// `KEYS[expr]`
+    KeysIndex(Box<Expr>),
+    /// `ARGV[expr]`
+    ArgvIndex(Box<Expr>),
+    BinOp(BinOp, Box<Expr>, Box<Expr>),
+    /// `let name = value` — evaluates to `value` and binds `name` for the
+    /// rest of the script; a binding made inside an `if` branch stays
+    /// visible after the `if`.
+    Let(String, Box<Expr>),
+    /// `if cond then block else block end`, each block a sequence whose
+    /// last expression's value is the block's value.
+    If(Box<Expr>, Vec<Expr>, Vec<Expr>),
+    /// `redis.call(cmd, arg, ...)`
+    RedisCall(Vec<Expr>),
+}
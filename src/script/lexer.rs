@@ -0,0 +1,146 @@
+//! Tokenizer for `EVAL` scripts.
+
+use super::ScriptError;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    Int(i64),
+    Str(Vec<u8>),
+    Ident(String),
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    Comma,
+    Dot,
+    Semicolon,
+    Assign,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    EqEq,
+    Neq,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+}
+
+pub fn tokenize(src: &[u8]) -> Result<Vec<Token>, ScriptError> {
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < src.len() {
+        let c = src[i];
+        match c {
+            b' ' | b'\t' | b'\r' | b'\n' => i += 1,
+            b'(' => {
+                tokens.push(Token::LParen);
+                i += 1;
+            }
+            b')' => {
+                tokens.push(Token::RParen);
+                i += 1;
+            }
+            b'[' => {
+                tokens.push(Token::LBracket);
+                i += 1;
+            }
+            b']' => {
+                tokens.push(Token::RBracket);
+                i += 1;
+            }
+            b',' => {
+                tokens.push(Token::Comma);
+                i += 1;
+            }
+            b'.' => {
+                tokens.push(Token::Dot);
+                i += 1;
+            }
+            b';' => {
+                tokens.push(Token::Semicolon);
+                i += 1;
+            }
+            b'+' => {
+                tokens.push(Token::Plus);
+                i += 1;
+            }
+            b'-' => {
+                tokens.push(Token::Minus);
+                i += 1;
+            }
+            b'*' => {
+                tokens.push(Token::Star);
+                i += 1;
+            }
+            b'/' => {
+                tokens.push(Token::Slash);
+                i += 1;
+            }
+            b'=' => {
+                if src.get(i + 1) == Some(&b'=') {
+                    tokens.push(Token::EqEq);
+                    i += 2;
+                } else {
+                    tokens.push(Token::Assign);
+                    i += 1;
+                }
+            }
+            b'!' if src.get(i + 1) == Some(&b'=') => {
+                tokens.push(Token::Neq);
+                i += 2;
+            }
+            b'<' => {
+                if src.get(i + 1) == Some(&b'=') {
+                    tokens.push(Token::Le);
+                    i += 2;
+                } else {
+                    tokens.push(Token::Lt);
+                    i += 1;
+                }
+            }
+            b'>' => {
+                if src.get(i + 1) == Some(&b'=') {
+                    tokens.push(Token::Ge);
+                    i += 2;
+                } else {
+                    tokens.push(Token::Gt);
+                    i += 1;
+                }
+            }
+            b'"' | b'\'' => {
+                let quote = c;
+                let start = i + 1;
+                let mut j = start;
+                while j < src.len() && src[j] != quote {
+                    j += 1;
+                }
+                if j >= src.len() {
+                    return Err(ScriptError::UnterminatedString);
+                }
+                tokens.push(Token::Str(src[start..j].to_vec()));
+                i = j + 1;
+            }
+            b'0'..=b'9' => {
+                let start = i;
+                while i < src.len() && src[i].is_ascii_digit() {
+                    i += 1;
+                }
+                let text = std::str::from_utf8(&src[start..i]).unwrap();
+                let n = text.parse::<i64>().map_err(|_| ScriptError::InvalidNumber)?;
+                tokens.push(Token::Int(n));
+            }
+            c if c.is_ascii_alphabetic() || c == b'_' => {
+                let start = i;
+                while i < src.len() && (src[i].is_ascii_alphanumeric() || src[i] == b'_') {
+                    i += 1;
+                }
+                let ident = std::str::from_utf8(&src[start..i]).unwrap().to_string();
+                tokens.push(Token::Ident(ident));
+            }
+            _ => return Err(ScriptError::UnexpectedByte(c)),
+        }
+    }
+    Ok(tokens)
+}
@@ -0,0 +1,211 @@
+//! `EVAL`: a tiny embedded language for atomic, server-side read-modify-write
+//! logic. `lexer` tokenizes the script, `parser` builds an `Expr` AST, and
+//! `eval` walks it, dispatching `redis.call` back through the normal
+//! command path.
+
+pub mod eval;
+pub mod expr;
+pub mod lexer;
+pub mod parser;
+pub mod value;
+
+pub use eval::RedisCaller;
+pub use value::ScriptValue;
+
+use crate::resp::ops::OpsError;
+use crate::types::{Key, Value};
+
+#[derive(Debug, PartialEq)]
+pub enum ScriptError {
+    UnexpectedByte(u8),
+    UnterminatedString,
+    InvalidNumber,
+    UnexpectedToken,
+    EmptyBlock,
+    UnknownVariable(String),
+    IndexOutOfBounds,
+    InvalidIndex,
+    InvalidCallArgs,
+    CallTranslate(OpsError),
+    TypeError,
+    DivideByZero,
+    IntegerOverflow,
+    NestingTooDeep,
+}
+
+/// Tokenizes, parses and evaluates `script` in one shot against the given
+/// `KEYS`/`ARGV`, dispatching any `redis.call`s through `caller`.
+pub fn run<C: RedisCaller>(
+    script: &[u8],
+    keys: &[Key],
+    args: &[Value],
+    caller: &mut C,
+) -> Result<ScriptValue, ScriptError> {
+    let tokens = lexer::tokenize(script)?;
+    let block = parser::parse(&tokens)?;
+    eval::eval_block(&block, keys, args, caller)
+}
+
+impl From<ScriptError> for crate::types::ReturnValue {
+    fn from(e: ScriptError) -> Self {
+        use crate::types::ReturnValue;
+        match e {
+            ScriptError::UnexpectedByte(_) => ReturnValue::Error(b"Bad Script!"),
+            ScriptError::UnterminatedString => ReturnValue::Error(b"Unterminated String!"),
+            ScriptError::InvalidNumber => ReturnValue::Error(b"Bad Number!"),
+            ScriptError::UnexpectedToken => ReturnValue::Error(b"Syntax Error!"),
+            ScriptError::EmptyBlock => ReturnValue::Error(b"Empty Block!"),
+            ScriptError::UnknownVariable(_) => ReturnValue::Error(b"Unknown Variable!"),
+            ScriptError::IndexOutOfBounds => ReturnValue::Error(b"Index Out Of Bounds!"),
+            ScriptError::InvalidIndex => ReturnValue::Error(b"Bad Index!"),
+            ScriptError::InvalidCallArgs => ReturnValue::Error(b"Bad Call Args!"),
+            ScriptError::CallTranslate(_) => ReturnValue::Error(b"Bad Call!"),
+            ScriptError::TypeError => ReturnValue::Error(b"Bad Type!"),
+            ScriptError::DivideByZero => ReturnValue::Error(b"Divide By Zero!"),
+            ScriptError::IntegerOverflow => ReturnValue::Error(b"Integer Overflow!"),
+            ScriptError::NestingTooDeep => ReturnValue::Error(b"Nesting Too Deep!"),
+        }
+    }
+}
+
+impl From<ScriptValue> for crate::types::ReturnValue {
+    fn from(v: ScriptValue) -> Self {
+        use crate::types::ReturnValue;
+        match v {
+            ScriptValue::Nil => ReturnValue::Nil,
+            ScriptValue::Int(n) => ReturnValue::IntRes(n),
+            ScriptValue::Bytes(b) => ReturnValue::StringRes(b),
+            ScriptValue::Array(items) => ReturnValue::Array(items.into_iter().map(ReturnValue::from).collect()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resp::ops::Ops;
+    use crate::types::ReturnValue;
+
+    /// A stub caller that answers every command with the fixed bytes
+    /// `echoed`, so tests can check `redis.call` dispatch without a real
+    /// engine.
+    struct EchoCaller;
+
+    impl RedisCaller for EchoCaller {
+        fn call(&mut self, _op: Ops) -> ReturnValue {
+            ReturnValue::StringRes(b"echoed".to_vec())
+        }
+    }
+
+    fn run_str(script: &str, keys: &[&str], args: &[&str]) -> Result<ScriptValue, ScriptError> {
+        let keys: Vec<Key> = keys.iter().map(|k| k.as_bytes().to_vec()).collect();
+        let args: Vec<Value> = args.iter().map(|a| a.as_bytes().to_vec()).collect();
+        run(script.as_bytes(), &keys, &args, &mut EchoCaller)
+    }
+
+    #[test]
+    fn arithmetic_and_comparison() {
+        assert_eq!(run_str("1 + 2 * 3", &[], &[]), Ok(ScriptValue::Int(7)));
+        assert_eq!(run_str("(1 + 2) * 3", &[], &[]), Ok(ScriptValue::Int(9)));
+        assert_eq!(run_str("2 < 3", &[], &[]), Ok(ScriptValue::Int(1)));
+        assert_eq!(run_str("2 == 3", &[], &[]), Ok(ScriptValue::Int(0)));
+    }
+
+    #[test]
+    fn let_and_var() {
+        assert_eq!(run_str("let x = 5; x + 1", &[], &[]), Ok(ScriptValue::Int(6)));
+    }
+
+    #[test]
+    fn if_then_else() {
+        assert_eq!(
+            run_str("if 1 then 10 else 20 end", &[], &[]),
+            Ok(ScriptValue::Int(10))
+        );
+        assert_eq!(
+            run_str("if 0 then 10 else 20 end", &[], &[]),
+            Ok(ScriptValue::Int(20))
+        );
+    }
+
+    #[test]
+    fn keys_and_argv_indexing() {
+        assert_eq!(
+            run_str("KEYS[0]", &["mykey"], &[]),
+            Ok(ScriptValue::Bytes(b"mykey".to_vec()))
+        );
+        assert_eq!(
+            run_str("ARGV[0]", &[], &["myval"]),
+            Ok(ScriptValue::Bytes(b"myval".to_vec()))
+        );
+    }
+
+    #[test]
+    fn index_out_of_bounds_is_an_error() {
+        assert_eq!(run_str("KEYS[0]", &[], &[]), Err(ScriptError::IndexOutOfBounds));
+    }
+
+    #[test]
+    fn divide_by_zero_is_an_error() {
+        assert_eq!(run_str("1 / 0", &[], &[]), Err(ScriptError::DivideByZero));
+    }
+
+    #[test]
+    fn arithmetic_overflow_is_an_error_instead_of_a_panic() {
+        assert_eq!(
+            run_str("9223372036854775807 + 1", &[], &[]),
+            Err(ScriptError::IntegerOverflow)
+        );
+        // The lexer/parser have no unary minus, so build `i64::MIN` via
+        // subtraction (this part doesn't overflow) before overflowing it.
+        assert_eq!(
+            run_str("0 - 9223372036854775807 - 1 - 1", &[], &[]),
+            Err(ScriptError::IntegerOverflow)
+        );
+        assert_eq!(
+            run_str("9223372036854775807 * 2", &[], &[]),
+            Err(ScriptError::IntegerOverflow)
+        );
+    }
+
+    #[test]
+    fn deeply_nested_parens_error_instead_of_overflowing_the_stack() {
+        let script = format!("{}1{}", "(".repeat(10_000), ")".repeat(10_000));
+        assert_eq!(run_str(&script, &[], &[]), Err(ScriptError::NestingTooDeep));
+    }
+
+    #[test]
+    fn deeply_nested_ifs_error_instead_of_overflowing_the_stack() {
+        let script = format!(
+            "{}1{}",
+            "if 1 then ".repeat(10_000),
+            " else 0 end".repeat(10_000)
+        );
+        assert_eq!(run_str(&script, &[], &[]), Err(ScriptError::NestingTooDeep));
+    }
+
+    #[test]
+    fn redis_call_goes_through_the_caller() {
+        assert_eq!(
+            run_str(r#"redis.call("get", KEYS[0])"#, &["k"], &[]),
+            Ok(ScriptValue::Bytes(b"echoed".to_vec()))
+        );
+    }
+
+    #[test]
+    fn script_error_converts_to_a_return_value_error() {
+        assert_eq!(
+            ReturnValue::from(ScriptError::DivideByZero),
+            ReturnValue::Error(b"Divide By Zero!")
+        );
+    }
+
+    #[test]
+    fn script_value_converts_to_a_return_value() {
+        assert_eq!(ReturnValue::from(ScriptValue::Int(5)), ReturnValue::IntRes(5));
+        assert_eq!(
+            ReturnValue::from(ScriptValue::Array(vec![ScriptValue::Int(1), ScriptValue::Nil])),
+            ReturnValue::Array(vec![ReturnValue::IntRes(1), ReturnValue::Nil])
+        );
+    }
+}
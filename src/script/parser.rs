@@ -0,0 +1,235 @@
+//! Recursive-descent parser turning a `Token` stream into a block of
+//! `Expr`s (a block is just a `;`-separated sequence; the last expression's
+//! value is the block's value).
+
+use super::expr::{BinOp, Expr};
+use super::lexer::Token;
+use super::ScriptError;
+
+/// Caps how deeply `if`s and parenthesized/indexed expressions may nest.
+/// A client-supplied script with unbounded nesting (e.g. 200,000 `(`s) would
+/// otherwise recurse the parser (and later the evaluator, over the AST it
+/// built) until it blows the stack -- an unrecoverable process abort, not a
+/// catchable error. 200 levels is far more than any real script needs and
+/// nowhere near enough to threaten the stack.
+const MAX_NESTING_DEPTH: usize = 200;
+
+pub fn parse(tokens: &[Token]) -> Result<Vec<Expr>, ScriptError> {
+    let mut p = Parser { tokens, pos: 0, depth: 0 };
+    let block = p.parse_block(&[])?;
+    if p.pos != p.tokens.len() {
+        return Err(ScriptError::UnexpectedToken);
+    }
+    Ok(block)
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn bump(&mut self) -> Option<&Token> {
+        let t = self.tokens.get(self.pos);
+        self.pos += 1;
+        t
+    }
+
+    fn expect(&mut self, tok: &Token) -> Result<(), ScriptError> {
+        if self.bump() == Some(tok) {
+            Ok(())
+        } else {
+            Err(ScriptError::UnexpectedToken)
+        }
+    }
+
+    fn expect_ident(&mut self, word: &str) -> Result<(), ScriptError> {
+        match self.bump() {
+            Some(Token::Ident(s)) if s == word => Ok(()),
+            _ => Err(ScriptError::UnexpectedToken),
+        }
+    }
+
+    fn at_ident(&self, word: &str) -> bool {
+        matches!(self.peek(), Some(Token::Ident(s)) if s == word)
+    }
+
+    /// Tracks recursion depth across the two constructs that actually
+    /// recurse -- nested `if`s (`parse_stmt`) and nested parens/indexing/
+    /// call args (`parse_primary`) -- so either one can be bounded by
+    /// `MAX_NESTING_DEPTH`.
+    fn enter_nesting(&mut self) -> Result<(), ScriptError> {
+        self.depth += 1;
+        if self.depth > MAX_NESTING_DEPTH {
+            return Err(ScriptError::NestingTooDeep);
+        }
+        Ok(())
+    }
+
+    fn leave_nesting(&mut self) {
+        self.depth -= 1;
+    }
+
+    /// Parses statements until a `;`-terminator run out or we hit one of
+    /// `stop_words` (used by `if`/`else`/`end`).
+    fn parse_block(&mut self, stop_words: &[&str]) -> Result<Vec<Expr>, ScriptError> {
+        let mut stmts = Vec::new();
+        loop {
+            if self.pos >= self.tokens.len() {
+                break;
+            }
+            if let Some(Token::Ident(s)) = self.peek() {
+                if stop_words.contains(&s.as_str()) {
+                    break;
+                }
+            }
+            stmts.push(self.parse_stmt()?);
+            if self.peek() == Some(&Token::Semicolon) {
+                self.bump();
+            } else {
+                break;
+            }
+        }
+        if stmts.is_empty() {
+            return Err(ScriptError::EmptyBlock);
+        }
+        Ok(stmts)
+    }
+
+    fn parse_stmt(&mut self) -> Result<Expr, ScriptError> {
+        self.enter_nesting()?;
+        let result = self.parse_stmt_inner();
+        self.leave_nesting();
+        result
+    }
+
+    fn parse_stmt_inner(&mut self) -> Result<Expr, ScriptError> {
+        if self.at_ident("let") {
+            self.bump();
+            let name = match self.bump() {
+                Some(Token::Ident(s)) => s.clone(),
+                _ => return Err(ScriptError::UnexpectedToken),
+            };
+            self.expect(&Token::Assign)?;
+            let value = self.parse_expr()?;
+            return Ok(Expr::Let(name, Box::new(value)));
+        }
+        if self.at_ident("if") {
+            self.bump();
+            let cond = self.parse_expr()?;
+            self.expect_ident("then")?;
+            let then_block = self.parse_block(&["else", "end"])?;
+            self.expect_ident("else")?;
+            let else_block = self.parse_block(&["end"])?;
+            self.expect_ident("end")?;
+            return Ok(Expr::If(Box::new(cond), then_block, else_block));
+        }
+        self.parse_expr()
+    }
+
+    fn parse_expr(&mut self) -> Result<Expr, ScriptError> {
+        self.parse_comparison()
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expr, ScriptError> {
+        let mut lhs = self.parse_additive()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::EqEq) => BinOp::Eq,
+                Some(Token::Neq) => BinOp::Neq,
+                Some(Token::Lt) => BinOp::Lt,
+                Some(Token::Gt) => BinOp::Gt,
+                Some(Token::Le) => BinOp::Le,
+                Some(Token::Ge) => BinOp::Ge,
+                _ => break,
+            };
+            self.bump();
+            let rhs = self.parse_additive()?;
+            lhs = Expr::BinOp(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_additive(&mut self) -> Result<Expr, ScriptError> {
+        let mut lhs = self.parse_multiplicative()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Plus) => BinOp::Add,
+                Some(Token::Minus) => BinOp::Sub,
+                _ => break,
+            };
+            self.bump();
+            let rhs = self.parse_multiplicative()?;
+            lhs = Expr::BinOp(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_multiplicative(&mut self) -> Result<Expr, ScriptError> {
+        let mut lhs = self.parse_primary()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Star) => BinOp::Mul,
+                Some(Token::Slash) => BinOp::Div,
+                _ => break,
+            };
+            self.bump();
+            let rhs = self.parse_primary()?;
+            lhs = Expr::BinOp(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, ScriptError> {
+        self.enter_nesting()?;
+        let result = self.parse_primary_inner();
+        self.leave_nesting();
+        result
+    }
+
+    fn parse_primary_inner(&mut self) -> Result<Expr, ScriptError> {
+        match self.bump().cloned() {
+            Some(Token::Int(n)) => Ok(Expr::Int(n)),
+            Some(Token::Str(s)) => Ok(Expr::Bytes(s)),
+            Some(Token::LParen) => {
+                let e = self.parse_expr()?;
+                self.expect(&Token::RParen)?;
+                Ok(e)
+            }
+            Some(Token::Ident(ident)) if ident == "KEYS" => {
+                self.expect(&Token::LBracket)?;
+                let idx = self.parse_expr()?;
+                self.expect(&Token::RBracket)?;
+                Ok(Expr::KeysIndex(Box::new(idx)))
+            }
+            Some(Token::Ident(ident)) if ident == "ARGV" => {
+                self.expect(&Token::LBracket)?;
+                let idx = self.parse_expr()?;
+                self.expect(&Token::RBracket)?;
+                Ok(Expr::ArgvIndex(Box::new(idx)))
+            }
+            Some(Token::Ident(ident)) if ident == "redis" => {
+                self.expect(&Token::Dot)?;
+                self.expect_ident("call")?;
+                self.expect(&Token::LParen)?;
+                let mut args = Vec::new();
+                if self.peek() != Some(&Token::RParen) {
+                    args.push(self.parse_expr()?);
+                    while self.peek() == Some(&Token::Comma) {
+                        self.bump();
+                        args.push(self.parse_expr()?);
+                    }
+                }
+                self.expect(&Token::RParen)?;
+                Ok(Expr::RedisCall(args))
+            }
+            Some(Token::Ident(ident)) => Ok(Expr::Var(ident)),
+            _ => Err(ScriptError::UnexpectedToken),
+        }
+    }
+}
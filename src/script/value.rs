@@ -0,0 +1,20 @@
+//! The value type scripts compute with: deliberately tiny, so it always has
+//! an unambiguous mapping to and from a `ReturnValue`.
+
+use crate::types::{Count, Value};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScriptValue {
+    Nil,
+    Int(Count),
+    Bytes(Value),
+    Array(Vec<ScriptValue>),
+}
+
+impl ScriptValue {
+    /// Truthiness for `if`: nil and zero are false, everything else
+    /// (including empty bytes/arrays) is true.
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, ScriptValue::Nil | ScriptValue::Int(0))
+    }
+}
@@ -0,0 +1,245 @@
+//! Set operations against `state.sets`, sharded like every other
+//! collection. The multi-key commands (`SDIFF`/`SUNION`/`SINTER`, `SMOVE`)
+//! lock every shard they touch up front, in ascending shard-index order, via
+//! `ShardedMap::read_many`/`write_many`. The `*STORE` variants go further:
+//! `dest`'s shard is locked together with every source shard in the same
+//! `write_many` call, so the read-compute-store is one atomic critical
+//! section instead of two.
+
+use crate::glob::glob_match;
+use crate::op_variants;
+use crate::types::{Count, Key, ReturnValue, StateRef, Value};
+use std::collections::HashSet;
+
+op_variants! {
+    SetOps,
+    SAdd(Key, Vec<Value>),
+    SRem(Key, Vec<Value>),
+    SMembers(Key),
+    SIsMember(Key, Value),
+    SCard(Key),
+    SDiff(Vec<Key>),
+    SUnion(Vec<Key>),
+    SInter(Vec<Key>),
+    SDiffStore(Key, Vec<Key>),
+    SUnionStore(Key, Vec<Key>),
+    SInterStore(Key, Vec<Key>),
+    SPop(Key, Option<Count>),
+    SMove(Key, Key, Value),
+    SRandMembers(Key, Option<Count>),
+    SScan(Key, Count, Option<Key>, Option<Count>)
+}
+
+const DEFAULT_SCAN_COUNT: Count = 10;
+
+fn members_array(set: &HashSet<Value>) -> ReturnValue {
+    ReturnValue::Array(set.iter().cloned().map(ReturnValue::StringRes).collect())
+}
+
+/// Clones out a consistent snapshot of each of `keys`' sets, locking every
+/// distinct shard they live in (ascending order) for the duration.
+fn read_many_sets(state: &StateRef, keys: &[Key]) -> Vec<HashSet<Value>> {
+    let key_refs: Vec<&Key> = keys.iter().collect();
+    let shards = state.sets.read_many(&key_refs);
+    keys.iter()
+        .map(|key| {
+            let idx = state.sets.shard_of(key);
+            shards
+                .iter()
+                .find(|(i, _)| *i == idx)
+                .and_then(|(_, shard)| shard.get(key))
+                .cloned()
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+/// Like `read_many_sets`, but also locks `dest`'s shard for writing and
+/// holds every lock for the whole read-compute-store: a concurrent write to
+/// any source key or to `dest` between the snapshot and the store would
+/// break the atomicity the `*STORE` commands need, so nothing is allowed to
+/// touch any of those shards until `dest` is written.
+fn store_combined(
+    state: &StateRef,
+    dest: Key,
+    keys: &[Key],
+    combine: impl Fn(HashSet<Value>, HashSet<Value>) -> HashSet<Value>,
+) -> ReturnValue {
+    let mut key_refs: Vec<&Key> = keys.iter().collect();
+    key_refs.push(&dest);
+    let mut shards = state.sets.write_many(&key_refs);
+
+    let per_key: Vec<HashSet<Value>> = keys
+        .iter()
+        .map(|key| {
+            let idx = state.sets.shard_of(key);
+            shards
+                .iter()
+                .find(|(i, _)| *i == idx)
+                .and_then(|(_, shard)| shard.get(key))
+                .cloned()
+                .unwrap_or_default()
+        })
+        .collect();
+    let mut per_key = per_key.into_iter();
+    let first = per_key.next().unwrap_or_default();
+    let result = per_key.fold(first, combine);
+    let len = result.len();
+
+    let dest_idx = state.sets.shard_of(&dest);
+    if let Some((_, shard)) = shards.iter_mut().find(|(i, _)| *i == dest_idx) {
+        shard.insert(dest, result);
+    }
+    ReturnValue::IntRes(len as Count)
+}
+
+pub async fn set_interact(op: SetOps, state: StateRef) -> ReturnValue {
+    match op {
+        SetOps::SAdd(key, members) => {
+            let mut shard = state.sets.write_shard(&key);
+            let set = shard.entry(key).or_default();
+            let added = members.into_iter().filter(|m| set.insert(m.clone())).count();
+            ReturnValue::IntRes(added as Count)
+        }
+        SetOps::SRem(key, members) => match state.sets.write_shard(&key).get_mut(&key) {
+            Some(set) => {
+                let removed = members.iter().filter(|m| set.remove(*m)).count();
+                ReturnValue::IntRes(removed as Count)
+            }
+            None => ReturnValue::IntRes(0),
+        },
+        SetOps::SMembers(key) => match state.sets.read_shard(&key).get(&key) {
+            Some(set) => members_array(set),
+            None => ReturnValue::Array(vec![]),
+        },
+        SetOps::SIsMember(key, member) => {
+            let is_member = state
+                .sets
+                .read_shard(&key)
+                .get(&key)
+                .is_some_and(|set| set.contains(&member));
+            ReturnValue::IntRes(if is_member { 1 } else { 0 })
+        }
+        SetOps::SCard(key) => state
+            .sets
+            .read_shard(&key)
+            .get(&key)
+            .map_or(0, |set| set.len() as Count)
+            .into(),
+        SetOps::SDiff(keys) => {
+            let mut sets = read_many_sets(&state, &keys).into_iter();
+            let first = sets.next().unwrap_or_default();
+            members_array(&sets.fold(first, |acc, set| &acc - &set))
+        }
+        SetOps::SUnion(keys) => {
+            let sets = read_many_sets(&state, &keys);
+            members_array(&sets.into_iter().fold(HashSet::new(), |acc, set| &acc | &set))
+        }
+        SetOps::SInter(keys) => {
+            let mut sets = read_many_sets(&state, &keys).into_iter();
+            let first = sets.next().unwrap_or_default();
+            members_array(&sets.fold(first, |acc, set| &acc & &set))
+        }
+        SetOps::SDiffStore(dest, keys) => store_combined(&state, dest, &keys, |acc, set| &acc - &set),
+        SetOps::SUnionStore(dest, keys) => store_combined(&state, dest, &keys, |acc, set| &acc | &set),
+        SetOps::SInterStore(dest, keys) => store_combined(&state, dest, &keys, |acc, set| &acc & &set),
+        SetOps::SPop(key, count) => {
+            let mut shard = state.sets.write_shard(&key);
+            match shard.get_mut(&key) {
+                None => match count {
+                    Some(_) => ReturnValue::Array(vec![]),
+                    None => ReturnValue::Nil,
+                },
+                Some(set) => {
+                    let n = count.unwrap_or(1).max(0) as usize;
+                    let popped: Vec<Value> = set.iter().take(n).cloned().collect();
+                    for member in &popped {
+                        set.remove(member);
+                    }
+                    match count {
+                        Some(_) => {
+                            ReturnValue::Array(popped.into_iter().map(ReturnValue::StringRes).collect())
+                        }
+                        None => popped
+                            .into_iter()
+                            .next()
+                            .map_or(ReturnValue::Nil, ReturnValue::StringRes),
+                    }
+                }
+            }
+        }
+        SetOps::SMove(src, dest, member) => {
+            let key_refs = [&src, &dest];
+            let mut shards = state.sets.write_many(&key_refs);
+            let src_idx = state.sets.shard_of(&src);
+            let dest_idx = state.sets.shard_of(&dest);
+            let removed = shards
+                .iter_mut()
+                .find(|(i, _)| *i == src_idx)
+                .and_then(|(_, shard)| shard.get_mut(&src))
+                .is_some_and(|set| set.remove(&member));
+            if removed {
+                if let Some((_, shard)) = shards.iter_mut().find(|(i, _)| *i == dest_idx) {
+                    shard.entry(dest).or_default().insert(member);
+                }
+                ReturnValue::IntRes(1)
+            } else {
+                ReturnValue::IntRes(0)
+            }
+        }
+        SetOps::SRandMembers(key, count) => match state.sets.read_shard(&key).get(&key) {
+            None => match count {
+                Some(_) => ReturnValue::Array(vec![]),
+                None => ReturnValue::Nil,
+            },
+            Some(set) => {
+                let n = count.unwrap_or(1).unsigned_abs() as usize;
+                let picked: Vec<ReturnValue> = set
+                    .iter()
+                    .take(n)
+                    .cloned()
+                    .map(ReturnValue::StringRes)
+                    .collect();
+                match count {
+                    Some(_) => ReturnValue::Array(picked),
+                    None => picked.into_iter().next().unwrap_or(ReturnValue::Nil),
+                }
+            }
+        },
+        SetOps::SScan(key, cursor, pattern, count) => {
+            let count = count.unwrap_or(DEFAULT_SCAN_COUNT).max(1);
+            let shard = state.sets.read_shard(&key);
+            let set = match shard.get(&key) {
+                Some(set) => set,
+                None => {
+                    return ReturnValue::Array(vec![
+                        ReturnValue::StringRes(b"0".to_vec()),
+                        ReturnValue::Array(vec![]),
+                    ])
+                }
+            };
+            let mut members: Vec<&Value> = set.iter().collect();
+            members.sort();
+            let start = cursor.max(0) as usize;
+            let mut elements = Vec::new();
+            let mut scanned = start;
+            for member in members.iter().skip(start) {
+                scanned += 1;
+                if let Some(pattern) = &pattern {
+                    if !glob_match(pattern, member) {
+                        continue;
+                    }
+                }
+                elements.push(ReturnValue::StringRes((*member).clone()));
+                if elements.len() as Count >= count {
+                    break;
+                }
+            }
+            let new_cursor = if scanned >= members.len() { 0 } else { scanned };
+            ReturnValue::Array(vec![
+                ReturnValue::StringRes(new_cursor.to_string().into_bytes()),
+                ReturnValue::Array(elements),
+            ])
+        }
+    }
+}
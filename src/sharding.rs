@@ -0,0 +1,171 @@
+//! A keyspace split into independently-locked shards, so single-key ops on
+//! keys in different shards never contend with each other.
+
+use parking_lot::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hash};
+
+/// Shard count used when a collection doesn't ask for a specific one.
+pub const DEFAULT_SHARD_COUNT: usize = 16;
+
+pub struct ShardedMap<K, V, S = RandomState> {
+    shards: Vec<RwLock<HashMap<K, V>>>,
+    hash_builder: S,
+}
+
+impl<K: Eq + Hash, V> ShardedMap<K, V, RandomState> {
+    pub fn new() -> Self {
+        Self::with_shard_count(DEFAULT_SHARD_COUNT)
+    }
+
+    pub fn with_shard_count(shard_count: usize) -> Self {
+        Self::with_hasher(shard_count, RandomState::new())
+    }
+}
+
+impl<K: Eq + Hash, V> Default for ShardedMap<K, V, RandomState> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Eq + Hash, V, S: BuildHasher> ShardedMap<K, V, S> {
+    pub fn with_hasher(shard_count: usize, hash_builder: S) -> Self {
+        let shard_count = shard_count.max(1);
+        let shards = (0..shard_count).map(|_| RwLock::new(HashMap::new())).collect();
+        ShardedMap { shards, hash_builder }
+    }
+
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// The index of the shard `key` lives in. Exposed so that callers
+    /// holding the result of `write_many` or `read_many` can find the guard
+    /// for a particular key without locking it a second time.
+    pub fn shard_of(&self, key: &K) -> usize {
+        (self.hash_builder.hash_one(key) as usize) % self.shards.len()
+    }
+
+    /// Locks the single shard holding `key` for reading.
+    pub fn read_shard(&self, key: &K) -> RwLockReadGuard<'_, HashMap<K, V>> {
+        self.shards[self.shard_of(key)].read()
+    }
+
+    /// Locks the single shard holding `key` for writing.
+    pub fn write_shard(&self, key: &K) -> RwLockWriteGuard<'_, HashMap<K, V>> {
+        self.shards[self.shard_of(key)].write()
+    }
+
+    /// Locks every distinct shard touched by `keys` for writing, always in
+    /// ascending shard-index order. Two calls that both go through this
+    /// method can never deadlock each other, no matter what order their
+    /// keys are named in. Callers look a given key's guard back up with
+    /// `shard_of` against the returned indices.
+    pub fn write_many(&self, keys: &[&K]) -> Vec<(usize, RwLockWriteGuard<'_, HashMap<K, V>>)> {
+        let mut indices: Vec<usize> = keys.iter().map(|k| self.shard_of(k)).collect();
+        indices.sort_unstable();
+        indices.dedup();
+        indices.into_iter().map(|i| (i, self.shards[i].write())).collect()
+    }
+
+    /// Read-only counterpart to `write_many`: locks every distinct shard
+    /// touched by `keys` for reading, in ascending order, without blocking
+    /// writers to shards none of `keys` live in.
+    pub fn read_many(&self, keys: &[&K]) -> Vec<(usize, RwLockReadGuard<'_, HashMap<K, V>>)> {
+        let mut indices: Vec<usize> = keys.iter().map(|k| self.shard_of(k)).collect();
+        indices.sort_unstable();
+        indices.dedup();
+        indices.into_iter().map(|i| (i, self.shards[i].read())).collect()
+    }
+
+    /// Locks every shard for reading, in ascending order.
+    pub fn read_all(&self) -> Vec<RwLockReadGuard<'_, HashMap<K, V>>> {
+        self.shards.iter().map(|s| s.read()).collect()
+    }
+
+    /// Clones the whole map out across every shard. Used by persistence,
+    /// where a globally consistent view isn't required.
+    pub fn snapshot(&self) -> HashMap<K, V>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        let mut out = HashMap::new();
+        for shard in self.read_all() {
+            out.extend(shard.iter().map(|(k, v)| (k.clone(), v.clone())));
+        }
+        out
+    }
+
+    /// Replaces the entire contents of the map with `entries`, redistributing
+    /// each one into its owning shard. Used when restoring a persisted dump.
+    pub fn replace_all(&self, entries: HashMap<K, V>) {
+        for shard in self.shards.iter() {
+            shard.write().clear();
+        }
+        for (key, value) in entries {
+            self.write_shard(&key).insert(key, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ShardedMap;
+
+    #[test]
+    fn read_write_shard_round_trip() {
+        let map: ShardedMap<String, i32> = ShardedMap::new();
+        map.write_shard(&"a".to_string()).insert("a".to_string(), 1);
+        assert_eq!(map.read_shard(&"a".to_string()).get("a"), Some(&1));
+        assert_eq!(map.read_shard(&"missing".to_string()).get("missing"), None);
+    }
+
+    #[test]
+    fn shard_of_is_stable_and_in_range() {
+        let map: ShardedMap<String, i32> = ShardedMap::with_shard_count(4);
+        let key = "some-key".to_string();
+        let idx = map.shard_of(&key);
+        assert!(idx < map.shard_count());
+        assert_eq!(idx, map.shard_of(&key));
+    }
+
+    #[test]
+    fn write_many_locks_every_distinct_shard_once() {
+        let map: ShardedMap<String, i32> = ShardedMap::with_shard_count(4);
+        let a = "a".to_string();
+        let b = "a".to_string(); // same key twice: should dedup to one guard
+        let c = "totally-different-key".to_string();
+        let guards = map.write_many(&[&a, &b, &c]);
+        let distinct_indices: std::collections::HashSet<usize> =
+            [map.shard_of(&a), map.shard_of(&c)].into_iter().collect();
+        assert_eq!(guards.len(), distinct_indices.len());
+    }
+
+    #[test]
+    fn snapshot_and_replace_all_round_trip() {
+        let map: ShardedMap<String, i32> = ShardedMap::new();
+        map.write_shard(&"a".to_string()).insert("a".to_string(), 1);
+        map.write_shard(&"b".to_string()).insert("b".to_string(), 2);
+        let snapshot = map.snapshot();
+        assert_eq!(snapshot.len(), 2);
+
+        let other: ShardedMap<String, i32> = ShardedMap::new();
+        other.replace_all(snapshot);
+        assert_eq!(other.read_shard(&"a".to_string()).get("a"), Some(&1));
+        assert_eq!(other.read_shard(&"b".to_string()).get("b"), Some(&2));
+    }
+
+    #[test]
+    fn replace_all_clears_stale_entries() {
+        let map: ShardedMap<String, i32> = ShardedMap::new();
+        map.write_shard(&"stale".to_string()).insert("stale".to_string(), 1);
+        let mut fresh = std::collections::HashMap::new();
+        fresh.insert("fresh".to_string(), 2);
+        map.replace_all(fresh);
+        assert_eq!(map.read_shard(&"stale".to_string()).get("stale"), None);
+        assert_eq!(map.read_shard(&"fresh".to_string()).get("fresh"), Some(&2));
+    }
+}
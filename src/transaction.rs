@@ -0,0 +1,108 @@
+//! Per-connection `MULTI`/`EXEC`/`DISCARD` queuing. `MULTI` moves a
+//! connection from `Normal` to `Queuing`, where every subsequently parsed
+//! `Ops` is buffered (the caller replies `+QUEUED`) instead of executed;
+//! `EXEC` drains the buffer for the caller to run; `DISCARD` throws it away.
+//! See `Engine::handle_op` for how the drained batch is actually run
+//! atomically now that there's no single global lock to hold across it.
+
+use crate::resp::ops::Ops;
+
+#[derive(Debug, Default)]
+pub enum ConnectionState {
+    #[default]
+    Normal,
+    Queuing(Vec<Ops>),
+}
+
+impl ConnectionState {
+    pub fn is_queuing(&self) -> bool {
+        matches!(self, ConnectionState::Queuing(_))
+    }
+
+    /// `MULTI`. Redis itself errors on a nested `MULTI`; since a connection
+    /// only ever queues one command at a time, re-entering just keeps the
+    /// existing buffer rather than losing it.
+    pub fn begin_multi(&mut self) {
+        if !self.is_queuing() {
+            *self = ConnectionState::Queuing(Vec::new());
+        }
+    }
+
+    /// Buffers `op`. Only meaningful while `Queuing`; callers should check
+    /// `is_queuing` first and run `op` immediately otherwise.
+    pub fn queue(&mut self, op: Ops) {
+        if let ConnectionState::Queuing(buffered) = self {
+            buffered.push(op);
+        }
+    }
+
+    /// `EXEC`: takes the buffered commands and resets to `Normal`. Empty if
+    /// we weren't in a transaction.
+    pub fn take_exec(&mut self) -> Vec<Ops> {
+        match std::mem::take(self) {
+            ConnectionState::Queuing(buffered) => buffered,
+            ConnectionState::Normal => Vec::new(),
+        }
+    }
+
+    /// `DISCARD`: drops any buffered commands and resets to `Normal`.
+    pub fn discard(&mut self) {
+        *self = ConnectionState::Normal;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn starts_normal_and_not_queuing() {
+        let conn = ConnectionState::default();
+        assert!(!conn.is_queuing());
+    }
+
+    #[test]
+    fn multi_then_queue_then_exec() {
+        let mut conn = ConnectionState::default();
+        conn.begin_multi();
+        assert!(conn.is_queuing());
+        conn.queue(Ops::Keys);
+        conn.queue(Ops::Pong);
+        let drained = conn.take_exec();
+        assert_eq!(drained.len(), 2);
+        assert!(!conn.is_queuing());
+    }
+
+    #[test]
+    fn queue_before_multi_is_a_no_op() {
+        let mut conn = ConnectionState::default();
+        conn.queue(Ops::Keys);
+        assert!(!conn.is_queuing());
+        assert_eq!(conn.take_exec().len(), 0);
+    }
+
+    #[test]
+    fn re_entering_multi_keeps_the_existing_buffer() {
+        let mut conn = ConnectionState::default();
+        conn.begin_multi();
+        conn.queue(Ops::Keys);
+        conn.begin_multi();
+        assert_eq!(conn.take_exec().len(), 1);
+    }
+
+    #[test]
+    fn discard_drops_the_buffer_and_returns_to_normal() {
+        let mut conn = ConnectionState::default();
+        conn.begin_multi();
+        conn.queue(Ops::Keys);
+        conn.discard();
+        assert!(!conn.is_queuing());
+        assert_eq!(conn.take_exec().len(), 0);
+    }
+
+    #[test]
+    fn exec_with_no_multi_returns_empty() {
+        let mut conn = ConnectionState::default();
+        assert_eq!(conn.take_exec().len(), 0);
+    }
+}
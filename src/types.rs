@@ -1,3 +1,7 @@
+use crate::sharding::ShardedMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
 /// These types are used by engine and ops to actually perform useful work.
 pub type Value = Vec<u8>;
 /// Key is the standard type to index our structures
@@ -5,6 +9,66 @@ pub type Key = Vec<u8>;
 /// Count is used for commands that count.
 pub type Count = i64;
 
+/// The engine's whole keyspace, one independently-locked [`ShardedMap`] per
+/// collection so that single-key operations on keys in different shards
+/// never contend with each other. See [`crate::sharding`] for the locking model.
+pub struct State {
+    pub kv: ShardedMap<Key, Value>,
+    pub hashes: ShardedMap<Key, HashMap<Key, Value>>,
+    pub sets: ShardedMap<Key, HashSet<Value>>,
+    pub lists: ShardedMap<Key, Vec<Value>>,
+    /// Reader/writer gate used by `Engine::handle_op` to make `EXEC` batches
+    /// atomic against concurrent commands from other connections: a single
+    /// command takes this as a reader (so unrelated commands still run
+    /// concurrently, same as today), while `EXEC` takes it as the sole
+    /// writer for its whole batch (and `EVAL` and `SAVE` for their whole
+    /// run). Unlike per-shard locking, this works
+    /// correctly no matter how many OS threads the runtime schedules
+    /// connections onto.
+    pub exec_lock: parking_lot::RwLock<()>,
+}
+
+impl State {
+    pub fn new() -> Self {
+        State {
+            kv: ShardedMap::new(),
+            hashes: ShardedMap::new(),
+            sets: ShardedMap::new(),
+            lists: ShardedMap::new(),
+            exec_lock: parking_lot::RwLock::new(()),
+        }
+    }
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Shared handle to the engine's state, cloned (cheaply, via `Arc`) into
+/// every connection task.
+pub type StateRef = Arc<State>;
+
+/// What a command's `*_interact` function hands back to the engine for
+/// encoding onto the wire.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReturnValue {
+    Ok,
+    StringRes(Value),
+    IntRes(Count),
+    Nil,
+    MultiStringRes(Vec<Value>),
+    Array(Vec<ReturnValue>),
+    Error(&'static [u8]),
+}
+
+impl From<Count> for ReturnValue {
+    fn from(n: Count) -> Self {
+        ReturnValue::IntRes(n)
+    }
+}
+
 #[allow(dead_code)]
 #[derive(Debug, PartialEq, Clone)]
 pub enum RedisValue {